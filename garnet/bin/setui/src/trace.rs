@@ -3,27 +3,109 @@
 // found in the LICENSE file.
 
 //! This mod provides utilities for simplifying the collection of trace events.
+//!
+//! Every macro takes an optional leading category argument and falls back to
+//! `"setui"` when it is omitted, so existing call sites keep working unchanged
+//! while other components can reuse the same helpers under their own category.
+//! The `trace_flow_*` macros thread a [`TracingNonce`] through
+//! [`fuchsia_trace::flow_begin`]/[`flow_step`]/[`flow_end`] so that work which
+//! crosses async boundaries and task handoffs stays correlated where
+//! `async_enter!` alone would lose the causal thread.
 
 /// A tracing nonce (not more than once) is a unique token used to keep track of async traces since
 /// normal tracing gets confused by the interleaved events that occur in async contexts. Use this
 /// nonce for any async tracing events you want to be correlated together. When generated by
 /// [fuchsia_trace::generate_nonce], it is guaranteed to be unique for all other nonces returned by
-/// that function.
+/// that function. It also serves as the flow id threaded through the `trace_flow_*` macros.
 pub type TracingNonce = u64;
 
-/// This macro simplifies collecting async trace events. It uses "setui" as the category name.
+/// This macro simplifies collecting async trace events. The category defaults to "setui" but may be
+/// overridden by passing it as the first argument.
 #[macro_export]
 macro_rules! trace {
     ($nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
         let _guard = ::fuchsia_trace::async_enter!($nonce, "setui", $name $(, $key => $val)*);
-    }
+    };
+    ($category:expr, $nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        let _guard = ::fuchsia_trace::async_enter!($nonce, $category, $name $(, $key => $val)*);
+    };
 }
 
 /// This macro simplifies collecting async trace events. It returns a guard that can be used to
-/// control the scope of the tracing event. It uses "setui" as the category name.
+/// control the scope of the tracing event. The category defaults to "setui" but may be overridden
+/// by passing it as the first argument.
 #[macro_export]
 macro_rules! trace_guard {
     ($nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
         ::fuchsia_trace::async_enter!($nonce, "setui", $name $(, $key => $val)*)
-    }
+    };
+    ($category:expr, $nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::async_enter!($nonce, $category, $name $(, $key => $val)*)
+    };
+}
+
+/// Begins a flow keyed by a [`TracingNonce`], correlating work that later continues on another task
+/// or async context. Pair with [`trace_flow_step!`]/[`trace_flow_end!`] using the same nonce. The
+/// category defaults to "setui" but may be overridden by passing it as the first argument.
+#[macro_export]
+macro_rules! trace_flow_begin {
+    ($nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::flow_begin!("setui", $name, $nonce $(, $key => $val)*);
+    };
+    ($category:expr, $nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::flow_begin!($category, $name, $nonce $(, $key => $val)*);
+    };
+}
+
+/// Records an intermediate step of a flow previously started with [`trace_flow_begin!`], keyed by
+/// the same [`TracingNonce`]. The category defaults to "setui" but may be overridden by passing it
+/// as the first argument.
+#[macro_export]
+macro_rules! trace_flow_step {
+    ($nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::flow_step!("setui", $name, $nonce $(, $key => $val)*);
+    };
+    ($category:expr, $nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::flow_step!($category, $name, $nonce $(, $key => $val)*);
+    };
+}
+
+/// Ends a flow previously started with [`trace_flow_begin!`], keyed by the same [`TracingNonce`].
+/// The category defaults to "setui" but may be overridden by passing it as the first argument.
+#[macro_export]
+macro_rules! trace_flow_end {
+    ($nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::flow_end!("setui", $name, $nonce $(, $key => $val)*);
+    };
+    ($category:expr, $nonce:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::flow_end!($category, $name, $nonce $(, $key => $val)*);
+    };
+}
+
+/// This macro simplifies collecting synchronous trace events. It binds a scope guard in the
+/// enclosing scope, so the measured duration lasts until the end of that scope; it yields no
+/// value. The category defaults to "setui" but may be overridden by passing it as the first
+/// argument.
+#[macro_export]
+macro_rules! trace_duration {
+    ($name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::duration!("setui", $name $(, $key => $val)*)
+    };
+    ($category:expr, $name:expr $(, $key:expr => $val:expr)* $(,)?) => {
+        ::fuchsia_trace::duration!($category, $name $(, $key => $val)*)
+    };
+}
+
+/// Records a numeric counter sample (e.g. a resource reading taken at the start and end of a
+/// scope). `id` disambiguates multiple counters sharing a name, and each `key => value` pair is one
+/// tracked series. The category defaults to "setui" but may be overridden by passing it as the
+/// first argument.
+#[macro_export]
+macro_rules! trace_counter {
+    ($name:expr, $id:expr $(, $key:expr => $val:expr)+ $(,)?) => {
+        ::fuchsia_trace::counter!("setui", $name, $id $(, $key => $val)+);
+    };
+    ($category:expr, $name:expr, $id:expr $(, $key:expr => $val:expr)+ $(,)?) => {
+        ::fuchsia_trace::counter!($category, $name, $id $(, $key => $val)+);
+    };
 }
@@ -0,0 +1,236 @@
+// Copyright 2021 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+use anyhow::Result;
+use std::io::Write;
+
+use crate::test_code::{CodeGenerator, TestCodeBuilder};
+use std::collections::BTreeSet;
+
+/// Selects which language backend the caller wants to generate.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TargetLanguage {
+    Rust,
+    Cpp,
+}
+
+pub struct CppTestCodeGenerator<'a> {
+    pub code: &'a CppTestCode,
+}
+
+impl CodeGenerator for CppTestCodeGenerator<'_> {
+    fn write_file<W: Write>(&self, writer: &mut W) -> Result<()> {
+        // Add include statements.
+        let all_includes = self.code.includes.clone().into_iter().collect::<Vec<_>>();
+        let mut includes = all_includes.join("\n");
+        includes.push_str("\n\n");
+        writer.write_all(&includes.as_bytes())?;
+
+        // Add constants, these are component urls.
+        if self.code.constants.len() > 0 {
+            let mut constants = self.code.constants.join("\n");
+            constants.push_str("\n\n");
+            writer.write_all(&constants.as_bytes())?;
+        }
+
+        // Add mock LocalComponentImpl subclasses, one per component.
+        if self.code.mock_classes.len() > 0 {
+            let mut mock_classes = self.code.mock_classes.join("\n\n");
+            mock_classes.push_str("\n\n");
+            writer.write_all(&mock_classes.as_bytes())?;
+        }
+
+        // Generate CreateRealm() helper.
+        let create_realm_start = r#"RealmRoot CreateRealm() {
+  auto builder = RealmBuilder::Create();
+"#;
+        let mut create_realm_impl = self.code.realm_builder_snippets.join("\n");
+        create_realm_impl.push_str("\n");
+        let create_realm_end = r#"  return builder.Build();
+}
+
+"#;
+        writer.write_all(&create_realm_start.as_bytes())?;
+        writer.write_all(&create_realm_impl.as_bytes())?;
+        writer.write_all(&create_realm_end.as_bytes())?;
+
+        // Add testcases, one per protocol.
+        let mut test_cases = self.code.test_case.join("\n\n");
+        test_cases.push_str("\n");
+        writer.write_all(&test_cases.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+pub struct CppTestCode {
+    /// `#include` strings
+    pub includes: BTreeSet<String>,
+    /// constant strings
+    constants: Vec<String>,
+    /// RealmBuilder routing code
+    pub realm_builder_snippets: Vec<String>,
+    /// testcase functions
+    test_case: Vec<String>,
+    /// mock LocalComponentImpl subclasses
+    mock_classes: Vec<String>,
+    /// var name used in generated RealmBuilder code that refers to the
+    /// component-under-test
+    component_under_test: String,
+}
+
+/// Renders a C++ `Ref` expression for a route source/target. `root` maps to the
+/// realm's parent and `self` to the component-under-test.
+fn cpp_ref(name: &str, component_under_test: &str) -> String {
+    match name {
+        "root" => "ParentRef{}".to_string(),
+        "self" => format!("ChildRef{{\"{}\"}}", component_under_test),
+        _ => format!("ChildRef{{\"{}\"}}", name),
+    }
+}
+
+impl TestCodeBuilder for CppTestCode {
+    fn new(component_name: &str) -> Self {
+        CppTestCode {
+            realm_builder_snippets: Vec::new(),
+            constants: Vec::new(),
+            includes: BTreeSet::new(),
+            test_case: Vec::new(),
+            mock_classes: Vec::new(),
+            component_under_test: component_name.to_string(),
+        }
+    }
+
+    fn add_import<'a>(&'a mut self, import_library: &str) -> &'a dyn TestCodeBuilder {
+        self.includes.insert(format!(r#"#include <{}>"#, import_library));
+        self
+    }
+
+    fn add_component<'a>(
+        &'a mut self,
+        component_name: &str,
+        url: &str,
+        const_var: &str,
+        mock: bool,
+    ) -> &'a dyn TestCodeBuilder {
+        if mock {
+            self.realm_builder_snippets.push(format!(
+                r#"  builder.AddLocalChild("{child_component}", [] {{
+    return std::make_unique<{class_name}>();
+  }});"#,
+                child_component = component_name,
+                class_name = format!("{}Impl", component_name),
+            ));
+        } else {
+            self.constants
+                .push(format!(r#"constexpr char {}[] = "{}";"#, const_var, url).to_string());
+            self.realm_builder_snippets.push(format!(
+                r#"  builder.AddChild("{child_component}", {url});"#,
+                child_component = component_name,
+                url = const_var,
+            ));
+        }
+        self
+    }
+
+    fn add_mock_impl<'a>(
+        &'a mut self,
+        component_name: &str,
+        _protocol: &str,
+    ) -> &'a dyn TestCodeBuilder {
+        // Note: this class name must match the one we added in 'add_component'.
+        let class_name = format!("{}Impl", component_name);
+        self.mock_classes.push(format!(
+            r#"class {class_name} : public LocalComponentImpl {{
+ public:
+  // TODO: Implement the behavior of this mock component.
+  void OnStart() override {{}}
+}};"#,
+            class_name = class_name,
+        ));
+        self
+    }
+
+    fn add_protocol<'a>(
+        &'a mut self,
+        protocol: &str,
+        source: &str,
+        targets: Vec<String>,
+    ) -> &'a dyn TestCodeBuilder {
+        let source_code = cpp_ref(source, &self.component_under_test);
+        let targets_code = targets
+            .iter()
+            .map(|t| cpp_ref(t, &self.component_under_test))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.realm_builder_snippets.push(format!(
+            r#"  builder.AddRoute(Route{{
+      .capabilities = {{Protocol{{"{protocol}"}}}},
+      .source = {from},
+      .targets = {{{to}}}}});"#,
+            protocol = protocol,
+            from = source_code,
+            to = targets_code,
+        ));
+        self
+    }
+
+    fn add_directory<'a>(
+        &'a mut self,
+        dir_name: &str,
+        _dir_path: &str,
+        targets: Vec<String>,
+    ) -> &'a dyn TestCodeBuilder {
+        let targets_code = targets
+            .iter()
+            .map(|t| cpp_ref(t, &self.component_under_test))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.realm_builder_snippets.push(format!(
+            r#"  builder.AddRoute(Route{{
+      .capabilities = {{Directory{{"{dir}"}}}},
+      .source = ParentRef{{}},
+      .targets = {{{to}}}}});"#,
+            dir = dir_name,
+            to = targets_code,
+        ));
+        self
+    }
+
+    fn add_storage<'a>(
+        &'a mut self,
+        storage_name: &str,
+        storage_path: &str,
+        targets: Vec<String>,
+    ) -> &'a dyn TestCodeBuilder {
+        let targets_code = targets
+            .iter()
+            .map(|t| cpp_ref(t, &self.component_under_test))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.realm_builder_snippets.push(format!(
+            r#"  builder.AddRoute(Route{{
+      .capabilities = {{Storage{{"{storage}", "{path}"}}}},
+      .source = ParentRef{{}},
+      .targets = {{{to}}}}});"#,
+            storage = storage_name,
+            path = storage_path,
+            to = targets_code,
+        ));
+        self
+    }
+
+    fn add_test_case<'a>(&'a mut self, protocol: &str) -> &'a dyn TestCodeBuilder {
+        self.test_case.push(format!(
+            r#"TEST_F(RealmTest, {protocol}Test) {{
+  auto realm = CreateRealm();
+  auto {var} = realm.component().Connect<{protocol}>();
+  // TODO: Exercise the {protocol} protocol through `{var}`.
+}}"#,
+            protocol = protocol,
+            var = protocol.to_ascii_lowercase(),
+        ));
+        self
+    }
+}
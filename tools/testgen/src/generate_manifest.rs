@@ -0,0 +1,195 @@
+// Copyright 2021 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Companion generators that turn a built [`RustTestCode`] into the component
+//! manifest (`meta/*.cml`) and build rules (`BUILD.gn`) needed to actually
+//! build and run the generated test, so one invocation produces a
+//! self-contained test directory rather than just a `.rs` file.
+
+use anyhow::Result;
+use std::io::Write;
+
+use crate::generate_rust_test::RustTestCode;
+use crate::validate::{CapabilityKind, CapabilityRoute};
+
+/// Renders the `meta/*.cml` test-root manifest for a [`RustTestCode`].
+pub struct ManifestGenerator<'a> {
+    pub code: &'a RustTestCode,
+}
+
+/// Returns the CML field keyword for a route's capability kind (`protocol`,
+/// `directory` or `storage`); the capability name itself is the field's value.
+fn cml_capability_field(route: &CapabilityRoute) -> &'static str {
+    match route.kind {
+        CapabilityKind::Protocol => "protocol",
+        CapabilityKind::Directory => "directory",
+        CapabilityKind::Storage => "storage",
+    }
+}
+
+/// Maps a route source to the `from` expression used in `offer`/`expose`.
+fn cml_source(source: &str) -> String {
+    match source {
+        "root" => "parent".to_string(),
+        "self" => "self".to_string(),
+        child => format!("#{}", child),
+    }
+}
+
+/// Default rights requested for directory capabilities; real tests usually
+/// narrow this, so it is emitted as a sensible, explicit starting point.
+const DEFAULT_DIRECTORY_RIGHTS: &'static str = r#"[ "r*" ]"#;
+
+/// Extra CML fields a directory/storage capability needs, each on its own
+/// line indented by `indent` spaces. `use` stanzas take the mount `path`
+/// (and `rights` for directories); `offer`/`expose` stanzas take at most
+/// `rights` — `path` is only valid on `use`. Set `with_path` accordingly.
+fn cml_extra_fields(route: &CapabilityRoute, indent: usize, with_path: bool) -> String {
+    let pad = " ".repeat(indent);
+    let mut extra = String::new();
+    if with_path {
+        if let Some(path) = &route.path {
+            extra.push_str(&format!("\n{}path: \"{}\",", pad, path));
+        }
+    }
+    if route.kind == CapabilityKind::Directory {
+        extra.push_str(&format!("\n{}rights: {},", pad, DEFAULT_DIRECTORY_RIGHTS));
+    }
+    extra
+}
+
+impl ManifestGenerator<'_> {
+    pub fn write_manifest<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str("    include: [ \"//src/sys/test_runners/rust/default.shard.cml\" ],\n");
+
+        // Declare the resolvable children.
+        if !self.code.children().is_empty() {
+            out.push_str("    children: [\n");
+            for child in self.code.children() {
+                out.push_str(&format!(
+                    "        {{\n            name: \"{}\",\n            url: \"{}\",\n        }},\n",
+                    child.name, child.url,
+                ));
+            }
+            out.push_str("    ],\n");
+        }
+
+        // Translate each route into a use/offer/expose stanza.
+        let mut uses = String::new();
+        let mut offers = String::new();
+        let mut exposes = String::new();
+        for route in self.code.routes() {
+            let field = cml_capability_field(route);
+            for target in &route.targets {
+                match target.as_str() {
+                    // The test root consumes the capability itself.
+                    "self" => uses.push_str(&format!(
+                        "        {{\n            {}: \"{}\",{}\n        }},\n",
+                        field,
+                        route.capability,
+                        cml_extra_fields(route, 12, /*with_path=*/ true),
+                    )),
+                    // The capability leaves the realm.
+                    "root" => exposes.push_str(&format!(
+                        "        {{\n            {}: \"{}\",\n            from: \"{}\",\n        }},\n",
+                        field,
+                        route.capability,
+                        cml_source(&route.source),
+                    )),
+                    // The capability is offered to another child.
+                    child => offers.push_str(&format!(
+                        "        {{\n            {}: \"{}\",\n            from: \"{}\",\n            to: \"#{}\",{}\n        }},\n",
+                        field,
+                        route.capability,
+                        cml_source(&route.source),
+                        child,
+                        cml_extra_fields(route, 12, /*with_path=*/ false),
+                    )),
+                }
+            }
+        }
+        if !uses.is_empty() {
+            out.push_str(&format!("    use: [\n{}    ],\n", uses));
+        }
+        if !offers.is_empty() {
+            out.push_str(&format!("    offer: [\n{}    ],\n", offers));
+        }
+        if !exposes.is_empty() {
+            out.push_str(&format!("    expose: [\n{}    ],\n", exposes));
+        }
+        out.push_str("}\n");
+
+        writer.write_all(out.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Renders the `BUILD.gn` fragment for a [`RustTestCode`].
+pub struct BuildGenerator<'a> {
+    pub code: &'a RustTestCode,
+    /// Base name of the generated test, used for the target and manifest names.
+    pub test_name: String,
+}
+
+/// Best-effort mapping from an imported library path to a GN dependency label.
+/// `fidl_fuchsia_foo::...` -> `//sdk/fidl/fuchsia.foo:fuchsia.foo_rust`; every
+/// other import maps to `//third_party/rust_crates:<crate>` followed by a TODO
+/// comment asking the user to confirm the label.
+fn gn_dep_for_import(library: &str) -> String {
+    let crate_root = library.split("::").next().unwrap_or(library);
+    if let Some(fidl) = crate_root.strip_prefix("fidl_") {
+        let dotted = fidl.replace('_', ".");
+        format!("\"//sdk/fidl/{dotted}:{dotted}_rust\",", dotted = dotted)
+    } else {
+        format!("\"//third_party/rust_crates:{}\", // TODO: confirm label", crate_root)
+    }
+}
+
+impl BuildGenerator<'_> {
+    pub fn write_build<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut deps: Vec<String> = self
+            .code
+            .import_libraries()
+            .iter()
+            .map(|lib| gn_dep_for_import(lib))
+            .collect();
+        deps.sort();
+        deps.dedup();
+
+        // Each dep already carries its own trailing comma (ahead of any inline
+        // TODO comment), so we only indent here.
+        let deps_block =
+            deps.iter().map(|d| format!("    {}", d)).collect::<Vec<_>>().join("\n");
+
+        let out = format!(
+            r#"import("//build/components.gni")
+import("//build/rust/rustc_test.gni")
+
+rustc_test("{name}_bin") {{
+  edition = "2021"
+  source_root = "src/lib.rs"
+  sources = [ "src/lib.rs" ]
+  deps = [
+{deps}
+  ]
+}}
+
+fuchsia_test_component("{name}_component") {{
+  manifest = "meta/{name}.cml"
+  deps = [ ":{name}_bin" ]
+}}
+
+fuchsia_test_package("{name}") {{
+  test_components = [ ":{name}_component" ]
+}}
+"#,
+            name = self.test_name,
+            deps = deps_block,
+        );
+        writer.write_all(out.as_bytes())?;
+        Ok(())
+    }
+}
@@ -3,32 +3,99 @@
 // found in the LICENSE file.
 
 use anyhow::Result;
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
 use std::io::Write;
 
 use crate::test_code::{CodeGenerator, TestCodeBuilder};
+use crate::validate::{CapabilityKind, CapabilityRoute};
 use std::collections::BTreeSet;
 
+const CREATE_REALM_TEMPLATE: &'static str = include_str!("templates/template_rust_create_realm");
 const MOCK_FUNC_TEMPLATE: &'static str = include_str!("templates/template_rust_mock_function");
 const TEST_FUNC_TEMPLATE: &'static str = include_str!("templates/template_rust_test_function");
 
+// Naming helpers used inside the templates so that generated identifiers can be
+// customized by editing the template files rather than recompiling the crate.
+handlebars_helper!(lower: |s: str| s.to_ascii_lowercase());
+handlebars_helper!(upper: |s: str| s.to_ascii_uppercase());
+handlebars_helper!(snake_case: |s: str| to_snake_case(s));
+handlebars_helper!(pascal_case: |s: str| to_pascal_case(s));
+
+/// Builds the shared Handlebars registry: the named templates that make up a
+/// generated test file plus the naming helpers referenced from them.
+fn build_registry() -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    // Templates are rendered verbatim; HTML escaping would corrupt the emitted Rust.
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars.register_helper("lower", Box::new(lower));
+    handlebars.register_helper("upper", Box::new(upper));
+    handlebars.register_helper("snake_case", Box::new(snake_case));
+    handlebars.register_helper("pascal_case", Box::new(pascal_case));
+    handlebars
+        .register_template_string("create_realm", CREATE_REALM_TEMPLATE)
+        .expect("valid create_realm template");
+    handlebars
+        .register_template_string("mock_function", MOCK_FUNC_TEMPLATE)
+        .expect("valid mock_function template");
+    handlebars
+        .register_template_string("test_case", TEST_FUNC_TEMPLATE)
+        .expect("valid test_case template");
+    handlebars
+}
+
+fn to_snake_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn to_pascal_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut capitalize = true;
+    for c in s.chars() {
+        if c == '_' || c == '-' {
+            capitalize = true;
+        } else if capitalize {
+            out.extend(c.to_ascii_uppercase().to_string().chars());
+            capitalize = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[derive(Serialize)]
+struct CreateRealmContext {
+    snippets: String,
+}
+
+#[derive(Serialize)]
+struct MockFunctionContext {
+    function_name: String,
+}
+
+#[derive(Serialize)]
+struct TestCaseContext {
+    protocol: String,
+}
+
 pub struct RustTestCodeGenerator<'a> {
     pub code: &'a RustTestCode,
 }
 
 impl CodeGenerator for RustTestCodeGenerator<'_> {
     fn write_file<W: Write>(&self, writer: &mut W) -> Result<()> {
-        let create_realm_func_start = r#"pub async fn create_realm() -> Result<RealmInstance, Error> {
-    let builder = RealmBuilder::new().await?;
-"#;
-
-        let mut create_realm_impl = self.code.realm_builder_snippets.join("\n");
-        create_realm_impl.push_str("\n");
-        let create_realm_func_end = r#"
-    let instance = builder.build().await?;
-    Ok(instance)
-}
-
-"#;
         // Add import statements
         let all_imports = self.code.imports.clone().into_iter().collect::<Vec<_>>();
         let mut imports = all_imports.join("\n");
@@ -40,10 +107,13 @@ impl CodeGenerator for RustTestCodeGenerator<'_> {
         constants.push_str("\n\n");
         writer.write_all(&constants.as_bytes())?;
 
-        // Generate create_realm() function
-        writer.write_all(&create_realm_func_start.as_bytes())?;
-        writer.write_all(&create_realm_impl.as_bytes())?;
-        writer.write_all(&create_realm_func_end.as_bytes())?;
+        // Generate create_realm() function from the routing snippets.
+        let create_realm = self.code.handlebars.render(
+            "create_realm",
+            &CreateRealmContext { snippets: self.code.realm_builder_snippets.join("\n") },
+        )?;
+        writer.write_all(&create_realm.as_bytes())?;
+        writer.write_all(b"\n\n")?;
 
         // Add mock implementation functions, one per component
         if self.code.mock_functions.len() > 0 {
@@ -75,6 +145,48 @@ pub struct RustTestCode {
     /// var name used in generated RealmBuilder code that refers to the
     /// component-under-test
     component_under_test: String,
+    /// Handlebars registry holding the named templates and naming helpers used
+    /// to render generated snippets.
+    handlebars: Handlebars<'static>,
+    /// Child component names declared through `add_component`, used by the
+    /// routing validation pass.
+    declared_components: BTreeSet<String>,
+    /// Capability routes recorded as they are added, used by the routing
+    /// validation pass.
+    routes: Vec<CapabilityRoute>,
+    /// Declared children with a resolvable URL (i.e. non-mock), in declaration
+    /// order. Used to render the CML `children` stanza.
+    children: Vec<ChildDecl>,
+    /// Libraries imported through `add_import`, used to derive BUILD.gn deps.
+    import_libraries: Vec<String>,
+}
+
+/// A child component with a resolvable URL, recorded for manifest generation.
+pub struct ChildDecl {
+    pub name: String,
+    pub url: String,
+}
+
+impl RustTestCode {
+    /// Child component names declared through `add_component`.
+    pub fn declared_components(&self) -> &BTreeSet<String> {
+        &self.declared_components
+    }
+
+    /// Capability routes recorded as the realm was built.
+    pub fn routes(&self) -> &[CapabilityRoute] {
+        &self.routes
+    }
+
+    /// Children declared with a resolvable URL, in declaration order.
+    pub fn children(&self) -> &[ChildDecl] {
+        &self.children
+    }
+
+    /// The libraries imported through `add_import`, in insertion order.
+    pub fn import_libraries(&self) -> &[String] {
+        &self.import_libraries
+    }
 }
 
 impl TestCodeBuilder for RustTestCode {
@@ -86,10 +198,17 @@ impl TestCodeBuilder for RustTestCode {
             test_case: Vec::new(),
             mock_functions: Vec::new(),
             component_under_test: component_name.to_string(),
+            handlebars: build_registry(),
+            declared_components: BTreeSet::new(),
+            routes: Vec::new(),
+            children: Vec::new(),
+            import_libraries: Vec::new(),
         }
     }
     fn add_import<'a>(&'a mut self, import_library: &str) -> &'a dyn TestCodeBuilder {
-        self.imports.insert(format!(r#"use {};"#, import_library));
+        if self.imports.insert(format!(r#"use {};"#, import_library)) {
+            self.import_libraries.push(import_library.to_string());
+        }
         self
     }
 
@@ -100,6 +219,7 @@ impl TestCodeBuilder for RustTestCode {
         const_var: &str,
         mock: bool,
     ) -> &'a dyn TestCodeBuilder {
+        self.declared_components.insert(component_name.to_string());
         if mock {
             let mock_function_name = format!("{}_impl", component_name);
             self.realm_builder_snippets.push(format!(
@@ -113,6 +233,7 @@ impl TestCodeBuilder for RustTestCode {
                 mock_function = &mock_function_name
             ));
         } else {
+            self.children.push(ChildDecl { name: component_name.to_string(), url: url.to_string() });
             self.constants.push(format!(r#"const {}: &str = "{}";"#, const_var, url).to_string());
             self.realm_builder_snippets.push(format!(
                 r#"    let {child_component} = builder.add_child(
@@ -135,7 +256,11 @@ impl TestCodeBuilder for RustTestCode {
     ) -> &'a dyn TestCodeBuilder {
         // Note: this function name must match the one we added in 'add_component'.
         let mock_function_name = format!("{}_impl", component_name);
-        self.mock_functions.push(MOCK_FUNC_TEMPLATE.replace("FUNCTION_NAME", &mock_function_name));
+        let rendered = self
+            .handlebars
+            .render("mock_function", &MockFunctionContext { function_name: mock_function_name })
+            .expect("render mock_function template");
+        self.mock_functions.push(rendered);
         self
     }
 
@@ -145,6 +270,13 @@ impl TestCodeBuilder for RustTestCode {
         source: &str,
         targets: Vec<String>,
     ) -> &'a dyn TestCodeBuilder {
+        self.routes.push(CapabilityRoute {
+            kind: CapabilityKind::Protocol,
+            capability: protocol.to_string(),
+            source: source.to_string(),
+            targets: targets.clone(),
+            path: None,
+        });
         let source_code = match source {
             "root" => "Ref::parent()".to_string(),
             "self" => format!("&{}", self.component_under_test),
@@ -160,7 +292,7 @@ impl TestCodeBuilder for RustTestCode {
                 targets_code
                     .push_str(format!("{:>16}.to(&{})\n", " ", self.component_under_test).as_str());
             } else {
-                targets_code.push_str(format!("{:>16}.to(&{})\n", " ", source).as_str());
+                targets_code.push_str(format!("{:>16}.to(&{})\n", " ", t).as_str());
             }
         }
         self.realm_builder_snippets.push(format!(
@@ -185,6 +317,13 @@ impl TestCodeBuilder for RustTestCode {
         dir_path: &str,
         targets: Vec<String>,
     ) -> &'a dyn TestCodeBuilder {
+        self.routes.push(CapabilityRoute {
+            kind: CapabilityKind::Directory,
+            capability: dir_name.to_string(),
+            source: "root".to_string(),
+            targets: targets.clone(),
+            path: Some(dir_path.to_string()),
+        });
         let mut targets_code: String = "".to_string();
         for i in 0..targets.len() {
             let t = &targets[i];
@@ -219,6 +358,13 @@ impl TestCodeBuilder for RustTestCode {
         storage_path: &str,
         targets: Vec<String>,
     ) -> &'a dyn TestCodeBuilder {
+        self.routes.push(CapabilityRoute {
+            kind: CapabilityKind::Storage,
+            capability: storage_name.to_string(),
+            source: "root".to_string(),
+            targets: targets.clone(),
+            path: Some(storage_path.to_string()),
+        });
         let mut targets_code: String = "".to_string();
         for i in 0..targets.len() {
             let t = &targets[i];
@@ -248,13 +394,11 @@ impl TestCodeBuilder for RustTestCode {
     }
 
     fn add_test_case<'a>(&'a mut self, protocol: &str) -> &'a dyn TestCodeBuilder {
-        let protocol_marker = format!("{}Marker", &protocol);
-        self.test_case.push(
-            TEST_FUNC_TEMPLATE
-                .replace("MARKER_VAR_NAME", &protocol_marker.to_ascii_lowercase())
-                .replace("MARKER", &protocol_marker)
-                .replace("PROTOCOL", &protocol),
-        );
+        let rendered = self
+            .handlebars
+            .render("test_case", &TestCaseContext { protocol: protocol.to_string() })
+            .expect("render test_case template");
+        self.test_case.push(rendered);
         self
     }
 }
@@ -0,0 +1,175 @@
+// Copyright 2021 The Fuchsia Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license that can be
+// found in the LICENSE file.
+
+//! Capability-routing validation over a built [`RustTestCode`] graph.
+//!
+//! Generation can happily emit a realm that references a child that was never
+//! declared, or declare a child that nothing routes to or from; such a test
+//! fails to compile or silently does nothing. This pass walks the declared components and the
+//! recorded routes and returns structured [`Diagnostic`]s so a front-end can
+//! print them or abort before writing a file.
+
+use crate::generate_rust_test::RustTestCode;
+
+/// Severity of a routing [`Diagnostic`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single finding produced by the routing validation pass.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// The capability or component the finding is about.
+    pub capability: String,
+    pub message: String,
+}
+
+/// The kind of capability a [`CapabilityRoute`] carries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CapabilityKind {
+    Protocol,
+    Directory,
+    Storage,
+}
+
+/// A route recorded as the realm is built: a capability offered `from` a source
+/// `to` a set of targets.
+#[derive(Clone, Debug)]
+pub struct CapabilityRoute {
+    pub kind: CapabilityKind,
+    pub capability: String,
+    pub source: String,
+    pub targets: Vec<String>,
+    /// Filesystem path for directory/storage capabilities; `None` for protocols.
+    pub path: Option<String>,
+}
+
+/// Collects findings as routes are checked, mirroring a lint-rule context.
+#[derive(Default)]
+pub struct LintContext {
+    findings: Vec<Diagnostic>,
+}
+
+impl LintContext {
+    pub fn new() -> Self {
+        LintContext { findings: Vec::new() }
+    }
+
+    pub fn error(&mut self, capability: &str, message: impl Into<String>) {
+        self.findings.push(Diagnostic {
+            severity: Severity::Error,
+            capability: capability.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn warning(&mut self, capability: &str, message: impl Into<String>) {
+        self.findings.push(Diagnostic {
+            severity: Severity::Warning,
+            capability: capability.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Returns the collected findings, most-recent last.
+    pub fn finish(self) -> Vec<Diagnostic> {
+        self.findings
+    }
+}
+
+/// `root` and `self` are always-valid pseudo-components; everything else must be
+/// a declared child.
+fn is_builtin(name: &str) -> bool {
+    name == "root" || name == "self"
+}
+
+/// Validates the capability graph of `code` and returns the diagnostics found.
+pub fn validate(code: &RustTestCode) -> Vec<Diagnostic> {
+    let declared = code.declared_components();
+    let routes = code.routes();
+    let mut ctx = LintContext::new();
+
+    // Components that participate in at least one route, so we can flag the rest.
+    let mut referenced: std::collections::BTreeSet<String> = std::collections::BTreeSet::new();
+    // Protocol capabilities seen, to flag duplicate routes.
+    let mut seen_protocols: std::collections::BTreeSet<String> = std::collections::BTreeSet::new();
+
+    for route in routes {
+        if !is_builtin(&route.source) {
+            if declared.contains(&route.source) {
+                referenced.insert(route.source.clone());
+            } else {
+                ctx.error(
+                    &route.capability,
+                    format!("route source `{}` is not a declared component", route.source),
+                );
+            }
+        }
+
+        for target in &route.targets {
+            if is_builtin(target) {
+                continue;
+            }
+            if declared.contains(target) {
+                referenced.insert(target.clone());
+            } else {
+                ctx.error(
+                    &route.capability,
+                    format!("route target `{}` is not a declared component", target),
+                );
+            }
+        }
+
+        if route.kind == CapabilityKind::Protocol
+            && !seen_protocols.insert(route.capability.clone())
+        {
+            ctx.warning(
+                &route.capability,
+                format!("protocol `{}` is routed more than once", route.capability),
+            );
+        }
+    }
+
+    for component in declared {
+        if !referenced.contains(component) {
+            ctx.warning(
+                component,
+                format!("component `{}` is declared but never routed to or from", component),
+            );
+        }
+    }
+
+    ctx.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_code::TestCodeBuilder;
+
+    #[test]
+    fn flags_unknown_target_and_unrouted_component() {
+        let mut code = RustTestCode::new("test-root");
+        code.add_component("echo_server", "#meta/echo_server.cm", "ECHO_URL", false);
+        code.add_component("unused", "#meta/unused.cm", "UNUSED_URL", false);
+        // Route to a component that was never declared.
+        code.add_protocol(
+            "fuchsia.example.Echo",
+            "echo_server",
+            vec!["ghost".to_string(), "root".to_string()],
+        );
+
+        let diagnostics = validate(&code);
+
+        assert!(diagnostics.iter().any(|d| d.severity == Severity::Error
+            && d.capability == "fuchsia.example.Echo"
+            && d.message.contains("ghost")));
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Warning && d.capability == "unused"));
+    }
+}